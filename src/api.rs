@@ -11,20 +11,30 @@
 use super::igd;
 use super::{
     bootstrap_cache::BootstrapCache,
-    config::{Config, SerialisableCertificate},
+    config::Config,
     connections::{Connection, IncomingConnections, SendStream},
     dirs::{Dirs, OverRide},
     error::{Error, Result},
     peer_config::{self, DEFAULT_IDLE_TIMEOUT_MSEC, DEFAULT_KEEP_ALIVE_INTERVAL_MSEC},
 };
 use bytes::Bytes;
-use futures::future::select_ok;
+use futures::{
+    future::{join_all, select_ok},
+    stream::Stream,
+};
 use log::{error, info, trace};
+use serde::{Deserialize, Serialize};
+use socket2::{Domain, Protocol, Socket, Type};
 use std::{
     collections::VecDeque,
-    mem,
+    fmt, io, mem,
     net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket},
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
 };
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::BroadcastStream;
 
 /// Default maximum allowed message size. We'll error out on any bigger messages and probably
 /// shutdown the connection. This value can be overridden via the `Config` option.
@@ -34,6 +44,16 @@ pub const DEFAULT_MAX_ALLOWED_MSG_SIZE: usize = 500 * 1024 * 1024; // 500MiB
 /// before using a random port.
 pub const DEFAULT_PORT_TO_TRY: u16 = 12000;
 
+/// Default duration, in seconds, of the UPnP port mapping we request from the router. Letting
+/// mappings auto-expire means a crashed node doesn't leave a stale port forwarded forever. The
+/// mapping is requested each time `QuicP2p::our_endpoint` is called; nothing renews it in the
+/// background. Can be overridden via `Config`.
+pub const DEFAULT_UPNP_LEASE_DURATION_SEC: u32 = 120;
+
+// Number of disconnection events an `Endpoint` will buffer for a slow `DisconnectionEvents`
+// subscriber before it starts missing them. Generous since these events are tiny and infrequent.
+const DISCONNECT_EVENTS_CHANNEL_CAPACITY: usize = 100;
+
 /// Message received from a peer
 pub enum Message {
     /// A message sent by peer on a uni-directional stream
@@ -54,9 +74,47 @@ pub enum Message {
     },
 }
 
-/// Host name of the Quic communication certificate used by peers
-// TODO: make it configurable
-const CERT_SERVER_NAME: &str = "MaidSAFE.net";
+/// A peer's stable identity, derived from the long-term Ed25519 public key embedded in the
+/// self-signed certificate it presents on every QUIC connection.
+///
+/// Unlike the connection's `SocketAddr`, a `PeerId` doesn't change as a peer moves between
+/// networks or NATs, so it's what should be used to recognise *who* you are talking to.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PeerId([u8; 32]);
+
+impl PeerId {
+    /// The raw bytes of the Ed25519 public key this id was derived from.
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// Build a `PeerId` from a raw Ed25519 public key.
+    pub(crate) fn from_public_key_bytes(bytes: &[u8]) -> Result<Self> {
+        let bytes: [u8; 32] = bytes.try_into().map_err(|_| {
+            Error::InvalidPeerCertificate(format!(
+                "expected a 32-byte Ed25519 public key, got {} bytes",
+                bytes.len()
+            ))
+        })?;
+
+        Ok(Self(bytes))
+    }
+}
+
+impl fmt::Debug for PeerId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PeerId({})", self)
+    }
+}
+
+impl fmt::Display for PeerId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for byte in &self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
 
 /// Main QuicP2p instance to communicate with QuicP2p using an async API
 #[derive(Clone)]
@@ -67,6 +125,9 @@ pub struct QuicP2p {
     bootstrap_cache: BootstrapCache,
     endpoint_cfg: quinn::ServerConfig,
     client_cfg: quinn::ClientConfig,
+    endpoint: Arc<Mutex<Option<Endpoint>>>,
+    our_id: PeerId,
+    cfg: Config,
 }
 
 impl QuicP2p {
@@ -108,10 +169,9 @@ impl QuicP2p {
             .keep_alive_interval_msec
             .unwrap_or(DEFAULT_KEEP_ALIVE_INTERVAL_MSEC);
 
-        let (key, cert) = {
-            let our_complete_cert: SerialisableCertificate = Default::default();
-            our_complete_cert.obtain_priv_key_and_cert()?
-        };
+        // Generates our long-term Ed25519 keypair and a self-signed certificate embedding its
+        // public key, which is what our `PeerId` is derived from.
+        let (key, cert, our_id) = peer_config::generate_self_signed_cert()?;
 
         let custom_dirs = cfg
             .bootstrap_cache_dir
@@ -119,7 +179,7 @@ impl QuicP2p {
             .map(|custom_dir| Dirs::Overide(OverRide::new(&custom_dir)));
 
         let mut bootstrap_cache =
-            BootstrapCache::new(cfg.hard_coded_contacts, custom_dirs.as_ref())?;
+            BootstrapCache::new(cfg.hard_coded_contacts.clone(), custom_dirs.as_ref())?;
         if use_bootstrap_cache {
             bootstrap_cache
                 .peers_mut()
@@ -128,10 +188,15 @@ impl QuicP2p {
             let _ = mem::replace(bootstrap_cache.peers_mut(), bootstrap_nodes);
         }
 
-        let endpoint_cfg =
-            peer_config::new_our_cfg(idle_timeout_msec, keep_alive_interval_msec, cert, key)?;
+        let endpoint_cfg = peer_config::new_our_cfg(
+            idle_timeout_msec,
+            keep_alive_interval_msec,
+            cert.clone(),
+            key.clone(),
+        )?;
 
-        let client_cfg = peer_config::new_client_cfg(idle_timeout_msec, keep_alive_interval_msec);
+        let client_cfg =
+            peer_config::new_client_cfg(idle_timeout_msec, keep_alive_interval_msec, cert, key)?;
 
         let quic_p2p = Self {
             local_addr: SocketAddr::new(ip, port),
@@ -140,11 +205,20 @@ impl QuicP2p {
             bootstrap_cache,
             endpoint_cfg,
             client_cfg,
+            endpoint: Arc::new(Mutex::new(None)),
+            our_id,
+            cfg,
         };
 
         Ok(quic_p2p)
     }
 
+    /// Our own `PeerId`, derived from our long-term public key, which other peers will see once
+    /// they authenticate a connection to us.
+    pub fn our_id(&self) -> PeerId {
+        self.our_id
+    }
+
     /// Bootstrap to the network.
     ///
     /// Bootstrap concept is different from "connect" in several ways: `bootstrap()` will try to
@@ -152,7 +226,10 @@ impl QuicP2p {
     /// previously cached.
     /// Once a connection with a peer succeeds, a `Connection` for such peer will be returned
     /// and all other connections will be dropped.
-    pub async fn bootstrap(&mut self) -> Result<Connection> {
+    ///
+    /// Alongside the connection, a `DisconnectionEvents` stream is returned which will yield
+    /// the peer's address once it disconnects.
+    pub async fn bootstrap(&mut self) -> Result<(Connection, DisconnectionEvents)> {
         // TODO: refactor bootstrap_cache so we can simply get the list of nodes
         let bootstrap_nodes: Vec<SocketAddr> = self
             .bootstrap_cache
@@ -164,104 +241,334 @@ impl QuicP2p {
             .collect();
 
         trace!("Bootstrapping with nodes {:?}", bootstrap_nodes);
+        let endpoint = self.endpoint().await?;
+        let disconnection_events = endpoint.disconnection_events();
+
         // Attempt to connect to all nodes and return the first one to succeed
         let mut tasks = Vec::default();
         for node_addr in bootstrap_nodes {
-            let endpoint_cfg = self.endpoint_cfg.clone();
-            let client_cfg = self.client_cfg.clone();
-            let max_msg_size = self.max_msg_size;
-            let local_addr = self.local_addr;
-            let allow_random_port = self.allow_random_port;
-            let task_handle = tokio::spawn(async move {
-                new_connection_to(
-                    &node_addr,
-                    endpoint_cfg,
-                    client_cfg,
-                    max_msg_size,
-                    local_addr,
-                    allow_random_port,
-                )
-                .await
-            });
-            tasks.push(task_handle);
+            let endpoint = endpoint.clone();
+            let task_handle =
+                tokio::spawn(async move { endpoint.connect_to(&node_addr, None).await });
+            // Flatten the task's result so that a failed connection attempt, not only a task
+            // that failed to run, counts as a failure for `select_ok`.
+            tasks.push(Box::pin(async move {
+                task_handle.await.map_err(|_| Error::BootstrapFailure)?
+            }));
         }
 
-        let (conn_info, _) = select_ok(tasks).await.map_err(|err| {
+        let (connection, _) = select_ok(tasks).await.map_err(|err| {
             error!("Failed to botstrap to the network: {}", err);
             Error::BootstrapFailure
         })?;
 
-        let (connection, addr) = conn_info?;
-        self.local_addr = addr;
-
-        Ok(connection)
+        Ok((connection, disconnection_events))
     }
 
     /// Connect to the given peer and return a `Connection` object if it succeeds,
     /// which can then be used to send messages to the connected peer.
-    pub async fn connect_to(&mut self, node_addr: &SocketAddr) -> Result<Connection> {
-        let (connection, addr) = new_connection_to(
-            node_addr,
+    ///
+    /// If `expected_id` is given, the connection is authenticated against it and
+    /// `Error::PeerIdMismatch` is returned if the peer we actually connected to presents a
+    /// different identity.
+    pub async fn connect_to(
+        &mut self,
+        node_addr: &SocketAddr,
+        expected_id: Option<PeerId>,
+    ) -> Result<Connection> {
+        let endpoint = self.endpoint().await?;
+        endpoint.connect_to(node_addr, expected_id).await
+    }
+
+    /// Obtain stream of incoming QUIC connections, together with a `DisconnectionEvents`
+    /// stream that yields the address of any peer whose connection subsequently closes.
+    pub async fn listen(&self) -> Result<(IncomingConnections, DisconnectionEvents)> {
+        let endpoint = self.endpoint().await?;
+        Ok((endpoint.listen()?, endpoint.disconnection_events()))
+    }
+
+    /// Get our connection adddress to give to others for them to connect to us.
+    ///
+    /// When built with the `upnp` feature, attempts to forward our port via UPnP/IGD, unless
+    /// `Config::is_client` is set - a pure client sitting behind a NAT has no listening port worth
+    /// forwarding, so it skips IGD entirely. The IGD mapping is not used to work out the address
+    /// returned here.
+    /// Will use hard coded contacts to ask for our endpoint. An unspecified address returned by a
+    /// contact is ignored, as such an address cannot be reached and hence is not useful. If no
+    /// contact is given, or none of them returns a usable address, we'll simply return the
+    /// address of the underlying bound socket, as is, even if it is unspecified.
+    ///
+    /// If more than one contact is queried, we only trust the result if every contact that
+    /// responded agrees on the same address - if they disagree (e.g. because they sit behind
+    /// different NATs) we have no way to tell which one, if any, is correct, so we fall back to
+    /// our bound socket address rather than returning a potentially wrong one.
+    ///
+    /// Unlike the UPnP/IGD mapping, this echo-service-based discovery doesn't require the `upnp`
+    /// feature and is always available.
+    pub async fn our_endpoint(&self) -> Result<SocketAddr> {
+        let endpoint = self.endpoint().await?;
+
+        #[cfg(feature = "upnp")]
+        if !self.cfg.is_client {
+            let lease_duration = self
+                .cfg
+                .upnp_lease_duration
+                .unwrap_or(DEFAULT_UPNP_LEASE_DURATION_SEC);
+
+            if let Err(err) = igd::forward_port(endpoint.socket_addr().port(), lease_duration).await
+            {
+                info!("Failed to map our port via UPnP/IGD: {}", err);
+            }
+        }
+
+        let hard_coded_contacts: Vec<SocketAddr> = self
+            .bootstrap_cache
+            .hard_coded_contacts()
+            .iter()
+            .cloned()
+            .collect();
+
+        if hard_coded_contacts.is_empty() {
+            return Ok(endpoint.socket_addr());
+        }
+
+        let mut tasks = Vec::default();
+        for node_addr in hard_coded_contacts {
+            let endpoint = endpoint.clone();
+            let task_handle =
+                tokio::spawn(async move { query_echo_service(&endpoint, &node_addr).await });
+            tasks.push(task_handle);
+        }
+
+        // Wait for every contact to respond (or fail) and only keep the addresses that are
+        // actually reachable, so a disagreement can be told apart from a contact we couldn't
+        // reach at all.
+        let mut observed_addrs = Vec::default();
+        for result in join_all(tasks).await {
+            match result {
+                Ok(Ok(addr)) if !addr.ip().is_unspecified() => observed_addrs.push(addr),
+                Ok(Ok(_)) => {
+                    info!("An echo service returned an unspecified address, ignoring it")
+                }
+                Ok(Err(err)) => info!("An echo service query failed: {}", err),
+                Err(err) => info!("An echo service query task failed to run: {}", err),
+            }
+        }
+
+        match observed_addrs.split_first() {
+            Some((first, rest)) if rest.iter().all(|addr| addr == first) => Ok(*first),
+            Some(_) => {
+                info!(
+                    "Echo services disagreed on our externally observed address, falling back to our bound socket address"
+                );
+                Ok(endpoint.socket_addr())
+            }
+            None => {
+                info!("Unable to reach any echo service, falling back to our bound socket address");
+                Ok(endpoint.socket_addr())
+            }
+        }
+    }
+
+    // Return our long-lived `Endpoint`, binding it the first time it's requested so that
+    // subsequent calls to `bootstrap`/`connect_to`/`listen` all dial out from and accept on
+    // the very same local address.
+    async fn endpoint(&self) -> Result<Endpoint> {
+        let mut guard = self.endpoint.lock().expect("poisoned lock");
+        if let Some(endpoint) = &*guard {
+            return Ok(endpoint.clone());
+        }
+
+        let (quinn_endpoint, quinn_incoming) = bind(
             self.endpoint_cfg.clone(),
-            self.client_cfg.clone(),
-            self.max_msg_size,
             self.local_addr,
             self.allow_random_port,
-        )
-        .await?;
+        )?;
+        let local_addr = quinn_endpoint.local_addr()?;
+        let (disconnect_tx, _) = broadcast::channel(DISCONNECT_EVENTS_CHANNEL_CAPACITY);
+
+        let endpoint = Endpoint {
+            local_addr,
+            quic_endpoint: quinn_endpoint,
+            quic_incoming: Arc::new(Mutex::new(Some(quinn_incoming))),
+            client_cfg: self.client_cfg.clone(),
+            max_msg_size: self.max_msg_size,
+            disconnect_tx,
+        };
+
+        *guard = Some(endpoint.clone());
+        Ok(endpoint)
+    }
+}
+
+/// A single, long-lived QUIC endpoint bound to one local address.
+///
+/// Unlike binding a fresh socket per connection, an `Endpoint` can be used to both dial out and
+/// accept incoming connections on the same port, which is what makes NAT hole-punching and
+/// consistent peer addressing possible.
+#[derive(Clone)]
+pub struct Endpoint {
+    local_addr: SocketAddr,
+    quic_endpoint: quinn::Endpoint,
+    // `quinn::Incoming` is a single-consumer stream, so it's taken out the first (and only)
+    // time `listen` is called.
+    quic_incoming: Arc<Mutex<Option<quinn::Incoming>>>,
+    client_cfg: quinn::ClientConfig,
+    max_msg_size: usize,
+    disconnect_tx: broadcast::Sender<SocketAddr>,
+}
+
+impl Endpoint {
+    /// Connect to the given peer, reusing this endpoint's bound socket to do so.
+    ///
+    /// If `expected_id` is given, the connection is authenticated against it and
+    /// `Error::PeerIdMismatch` is returned if the peer we actually connected to presents a
+    /// different identity.
+    pub async fn connect_to(
+        &self,
+        node_addr: &SocketAddr,
+        expected_id: Option<PeerId>,
+    ) -> Result<Connection> {
+        trace!("Attempting to connect to peer: {}", node_addr);
+        let quinn_connecting = self.quic_endpoint.connect_with(
+            self.client_cfg.clone(),
+            &node_addr,
+            peer_config::SERVER_NAME,
+        )?;
+
+        let quinn::NewConnection {
+            connection: quic_conn,
+            ..
+        } = quinn_connecting.await?;
+
+        trace!("Successfully connected to peer: {}", node_addr);
+
+        watch_for_disconnection(quic_conn.clone(), *node_addr, self.disconnect_tx.clone());
+
+        let connection = Connection::new(quic_conn, self.max_msg_size).await?;
+
+        if let Some(expected_id) = expected_id {
+            if connection.peer_id() != expected_id {
+                return Err(Error::PeerIdMismatch {
+                    expected: expected_id,
+                    actual: connection.peer_id(),
+                });
+            }
+        }
 
         Ok(connection)
     }
 
-    /// Obtain stream of incoming QUIC connections
+    /// Obtain the stream of incoming QUIC connections accepted on this endpoint.
+    ///
+    /// Can only be called once per `Endpoint` - `quinn::Incoming` is a single-consumer stream.
     pub fn listen(&self) -> Result<IncomingConnections> {
-        let (_, quinn_incoming) = bind(
-            self.endpoint_cfg.clone(),
-            self.local_addr,
-            self.allow_random_port,
-        )?;
-        IncomingConnections::new(quinn_incoming, self.max_msg_size)
+        let quinn_incoming = self
+            .quic_incoming
+            .lock()
+            .expect("poisoned lock")
+            .take()
+            .ok_or(Error::EndpointAlreadyListening)?;
+
+        IncomingConnections::new(quinn_incoming, self.max_msg_size, self.disconnect_tx.clone())
     }
 
-    /// Get our connection adddress to give to others for them to connect to us.
+    /// Obtain the stream of disconnection events for peers connected to or accepted by this
+    /// endpoint, i.e. a peer's address is yielded once its underlying `quinn::Connection`
+    /// closes, whether due to an idle timeout, the application closing it, or a transport error.
     ///
-    /// Attempts to use UPnP to automatically find the public endpoint and forward a port.
-    /// Will use hard coded contacts to ask for our endpoint. If no contact is given then we'll
-    /// simply build our connection info by querying the underlying bound socket for our address.
-    /// Note that if such an obtained address is of unspecified category we will ignore that as
-    /// such an address cannot be reached and hence not useful.
-    #[cfg(feature = "upnp")]
-    pub fn our_endpoint(&self) -> Result<SocketAddr> {
-        // TODO: make use of IGD and echo services
-        Ok(self.local_addr)
+    /// Can be called as many times as needed - each call subscribes independently and is handed
+    /// every disconnection that occurs from that point on, so e.g. both `bootstrap` and `listen`
+    /// can obtain their own stream from the same `Endpoint`.
+    pub fn disconnection_events(&self) -> DisconnectionEvents {
+        DisconnectionEvents(BroadcastStream::new(self.disconnect_tx.subscribe()))
+    }
+
+    /// The local address this endpoint is bound to.
+    pub fn socket_addr(&self) -> SocketAddr {
+        self.local_addr
     }
 }
 
-// Creates a new Connection
-async fn new_connection_to(
-    node_addr: &SocketAddr,
-    endpoint_cfg: quinn::ServerConfig,
-    client_cfg: quinn::ClientConfig,
-    max_msg_size: usize,
-    local_addr: SocketAddr,
-    allow_random_port: bool,
-) -> Result<(Connection, SocketAddr)> {
-    trace!("Attempting to connect to peer: {}", node_addr);
-    let (quinn_endpoint, _) = bind(endpoint_cfg, local_addr, allow_random_port)?;
+/// Stream of the addresses of peers as their connection to us closes, whether due to an idle
+/// timeout, the application closing it, or a transport error.
+///
+/// Backed by a broadcast channel, so a subscriber that falls too far behind (more than
+/// `DISCONNECT_EVENTS_CHANNEL_CAPACITY` events) silently skips the ones it missed rather than
+/// blocking the endpoint - losing a disconnection notification isn't fatal, the next attempt to
+/// use that peer's connection will fail on its own.
+pub struct DisconnectionEvents(BroadcastStream<SocketAddr>);
+
+impl Stream for DisconnectionEvents {
+    type Item = SocketAddr;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        loop {
+            return match Pin::new(&mut self.0).poll_next(cx) {
+                Poll::Ready(Some(Ok(peer_addr))) => Poll::Ready(Some(peer_addr)),
+                Poll::Ready(Some(Err(_lagged))) => continue,
+                Poll::Ready(None) => Poll::Ready(None),
+                Poll::Pending => Poll::Pending,
+            };
+        }
+    }
+}
+
+// Spawn a task that waits for `quic_conn` to close and forwards the peer's address through
+// `disconnect_tx`, so callers of `DisconnectionEvents` learn about the disconnection instead of
+// only finding out on the next failed send. Used on both the dialing side (`Endpoint::connect_to`)
+// and the accepting side (`connections::IncomingConnections`).
+pub(crate) fn watch_for_disconnection(
+    quic_conn: quinn::Connection,
+    peer_addr: SocketAddr,
+    disconnect_tx: broadcast::Sender<SocketAddr>,
+) {
+    let _ = tokio::spawn(async move {
+        quic_conn.closed().await;
+        trace!("Connection to peer {} closed", peer_addr);
+        let _ = disconnect_tx.send(peer_addr);
+    });
+}
 
-    let quinn_connecting = quinn_endpoint.connect_with(client_cfg, &node_addr, CERT_SERVER_NAME)?;
+/// Every message carried on a bidirectional stream, tagged so that our internal echo service
+/// protocol (used to learn the address we are observed to be connecting from, for nodes behind a
+/// NAT that can't otherwise find out their own publicly reachable address) can never be confused
+/// with an application message - the two no longer share a wire format that a probe could
+/// mistake one for the other, they're distinct variants of the same enum.
+#[derive(Serialize, Deserialize)]
+pub(crate) enum BiStreamMessage {
+    /// Sent by a node asking its peer what address it is seeing it connect from.
+    EchoServiceReq,
+    /// Sent back in response to an `EchoServiceReq`, carrying the remote peer's observed address.
+    EchoServiceResp(SocketAddr),
+    /// An application-level message, opaque to us.
+    User(Bytes),
+}
 
-    let quinn::NewConnection {
-        connection: quic_conn,
-        ..
-    } = quinn_connecting.await?;
+// Connect to `node_addr`, ask it to echo back the address it observed us connecting from.
+async fn query_echo_service(endpoint: &Endpoint, node_addr: &SocketAddr) -> Result<SocketAddr> {
+    let connection = endpoint.connect_to(node_addr, None).await?;
 
-    trace!("Successfully connected to peer: {}", node_addr);
+    let request = Bytes::from(bincode::serialize(&BiStreamMessage::EchoServiceReq)?);
+    let response = connection.send_bi_raw(request).await?;
 
-    Ok((
-        Connection::new(quic_conn, max_msg_size).await?,
-        quinn_endpoint.local_addr()?,
-    ))
+    match bincode::deserialize(&response)? {
+        BiStreamMessage::EchoServiceResp(addr) => Ok(addr),
+        _ => Err(Error::EchoServiceFailure(
+            "peer replied with something other than an echo service response".to_string(),
+        )),
+    }
+}
+
+// Build the reply a node should send back upon receiving an `EchoServiceReq` on a bidirectional
+// stream: the address it observed as the remote peer of that connection.
+//
+// Invoked by `connections::IncomingConnections` when it dispatches a bidirectional message that
+// turns out to be an echo service request, before handing any other message on to the caller.
+pub(crate) fn echo_service_response(remote_addr: SocketAddr) -> Result<Bytes> {
+    Ok(Bytes::from(bincode::serialize(
+        &BiStreamMessage::EchoServiceResp(remote_addr),
+    )?))
 }
 
 // Bind a new socket with a local address
@@ -273,7 +580,7 @@ fn bind(
     let mut endpoint_builder = quinn::Endpoint::builder();
     let _ = endpoint_builder.listen(endpoint_cfg);
 
-    match UdpSocket::bind(&local_addr) {
+    match new_udp_socket(&local_addr) {
         Ok(udp) => endpoint_builder.with_socket(udp).map_err(Error::Endpoint),
         Err(err) if allow_random_port => {
             info!(
@@ -282,7 +589,12 @@ fn bind(
             );
             let bind_addr = SocketAddr::new(local_addr.ip(), 0);
 
-            endpoint_builder.bind(&bind_addr).map_err(|e| {
+            let udp = new_udp_socket(&bind_addr).map_err(|e| {
+                error!("Failed to bind to random port {:?}", e);
+                Error::Endpoint(e)
+            })?;
+
+            endpoint_builder.with_socket(udp).map_err(|e| {
                 error!("Failed to bind to random port {:?}", e);
                 Error::Endpoint(e)
             })
@@ -297,6 +609,92 @@ fn bind(
     }
 }
 
+/// Size of the socket buffers requested for the UDP socket the endpoint binds, large enough to
+/// absorb a burst of datagrams - including the coalesced ones GSO/GRO below produce - without the
+/// kernel dropping them.
+const UDP_SOCKET_BUFFER_SIZE: usize = 2 * 1024 * 1024;
+
+/// Segment size requested for outgoing GSO batches: conservative enough to stay under the IPv6
+/// minimum MTU (1280 bytes) once the IPv6 and UDP headers (48 bytes) are added, so a batched send
+/// never needs to fragment regardless of path MTU.
+const GSO_SEGMENT_SIZE: usize = 1200;
+
+// Bind a UDP socket with larger-than-default kernel send/receive buffers, and - on Linux - the
+// actual kernel offload features that let `quinn` coalesce many datagrams into a single
+// `sendmsg`/`recvmsg` syscall: `UDP_SEGMENT` for GSO on send, `UDP_GRO` for its receive-side
+// equivalent. Growing the buffers alone does not enable either of those; it only gives the kernel
+// enough headroom to hold a burst of datagrams - whether or not that burst was produced by
+// GSO/GRO batching - instead of silently dropping them.
+fn new_udp_socket(local_addr: &SocketAddr) -> io::Result<UdpSocket> {
+    let socket = Socket::new(Domain::for_address(*local_addr), Type::DGRAM, Some(Protocol::UDP))?;
+
+    if let Err(err) = socket.set_recv_buffer_size(UDP_SOCKET_BUFFER_SIZE) {
+        info!("Failed to grow the UDP receive buffer: {}", err);
+    }
+    if let Err(err) = socket.set_send_buffer_size(UDP_SOCKET_BUFFER_SIZE) {
+        info!("Failed to grow the UDP send buffer: {}", err);
+    }
+
+    enable_gso_gro(&socket);
+
+    socket.bind(&(*local_addr).into())?;
+    Ok(socket.into())
+}
+
+// Toggle the kernel offload options `quinn`'s UDP backend uses to batch sends/receives. Neither
+// option is exposed by `socket2`, and `UDP_SEGMENT`/`UDP_GRO` aren't in the `libc` crate either
+// (they're a relatively recent addition to `linux/udp.h`), so we set them with a raw
+// `setsockopt`. Both are advisory: on a kernel or NIC that doesn't support them the call simply
+// fails and we fall back to one datagram per syscall, which is always safe.
+#[cfg(target_os = "linux")]
+fn enable_gso_gro(socket: &Socket) {
+    use std::os::unix::io::AsRawFd;
+
+    const UDP_SEGMENT: libc::c_int = 103;
+    const UDP_GRO: libc::c_int = 104;
+
+    let fd = socket.as_raw_fd();
+    let gso_segment_size: libc::c_int = GSO_SEGMENT_SIZE as libc::c_int;
+    let gro_enabled: libc::c_int = 1;
+
+    // SAFETY: `fd` is a valid, open, not-yet-bound UDP socket for the duration of this call, and
+    // each option's pointer/length matches the `libc::c_int` it points at.
+    unsafe {
+        if libc::setsockopt(
+            fd,
+            libc::SOL_UDP,
+            UDP_SEGMENT,
+            &gso_segment_size as *const libc::c_int as *const libc::c_void,
+            mem::size_of::<libc::c_int>() as libc::socklen_t,
+        ) != 0
+        {
+            info!(
+                "Failed to enable UDP GSO, falling back to one datagram per syscall: {}",
+                io::Error::last_os_error()
+            );
+        }
+
+        if libc::setsockopt(
+            fd,
+            libc::SOL_UDP,
+            UDP_GRO,
+            &gro_enabled as *const libc::c_int as *const libc::c_void,
+            mem::size_of::<libc::c_int>() as libc::socklen_t,
+        ) != 0
+        {
+            info!(
+                "Failed to enable UDP GRO, falling back to one datagram per syscall: {}",
+                io::Error::last_os_error()
+            );
+        }
+    }
+}
+
+// GSO/GRO are Linux-specific kernel features; other platforms fall back transparently to sending
+// and receiving one datagram per syscall.
+#[cfg(not(target_os = "linux"))]
+fn enable_gso_gro(_socket: &Socket) {}
+
 // Private helpers
 
 // Unwrap the conffig if provided by the user, otherwise construct the default one
@@ -0,0 +1,64 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+//! User-supplied configuration for a `QuicP2p` instance.
+
+use super::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    net::{IpAddr, SocketAddr},
+    path::Path,
+};
+
+/// Configuration for `QuicP2p`.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Config {
+    /// Port we want to bind to.
+    pub port: Option<u16>,
+    /// IP address to bind to, e.g. `0.0.0.0` for all interfaces.
+    pub ip: Option<IpAddr>,
+    /// Maximum allowed message size, overriding `DEFAULT_MAX_ALLOWED_MSG_SIZE`.
+    pub max_msg_size_allowed: Option<u32>,
+    /// Duration of inactivity after which a connection is considered dead, overriding
+    /// `peer_config::DEFAULT_IDLE_TIMEOUT_MSEC`.
+    pub idle_timeout_msec: Option<u64>,
+    /// Interval at which we send keep-alives to connected peers, overriding
+    /// `peer_config::DEFAULT_KEEP_ALIVE_INTERVAL_MSEC`.
+    pub keep_alive_interval_msec: Option<u64>,
+    /// Override the default directory where the bootstrap cache file is stored.
+    pub bootstrap_cache_dir: Option<String>,
+    /// Hard-coded contacts, tried on every `bootstrap` alongside any cached peers.
+    pub hard_coded_contacts: HashSet<SocketAddr>,
+    /// Lease duration, in seconds, requested for our UPnP/IGD port mapping, overriding
+    /// `DEFAULT_UPNP_LEASE_DURATION_SEC`. Has no effect if `is_client` is set.
+    pub upnp_lease_duration: Option<u32>,
+    /// Mark this node as a pure client sitting behind a NAT with no listening port worth
+    /// advertising, so it skips UPnP/IGD port mapping entirely.
+    pub is_client: bool,
+}
+
+impl Config {
+    /// Read the config from `path`, falling back to the default config if no path is given or
+    /// `path` doesn't point at an existing file.
+    pub fn read_or_construct_default(path: Option<&Path>) -> Result<Self> {
+        let path = match path {
+            Some(path) if path.exists() => path,
+            _ => return Ok(Self::default()),
+        };
+
+        let bytes = std::fs::read(path).map_err(|e| Error::Configuration {
+            e: format!("failed to read config file {}: {}", path.display(), e),
+        })?;
+
+        serde_json::from_slice(&bytes).map_err(|e| Error::Configuration {
+            e: format!("failed to parse config file {}: {}", path.display(), e),
+        })
+    }
+}
@@ -0,0 +1,291 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+//! Established QUIC connections: sending messages to a peer we dialed, and accepting connections
+//! and their messages from peers that dialed us.
+
+use super::{
+    api::{echo_service_response, watch_for_disconnection, BiStreamMessage, Message, PeerId},
+    error::{Error, Result},
+    peer_config,
+};
+use bytes::Bytes;
+use futures::{
+    channel::mpsc,
+    stream::{Stream, StreamExt},
+};
+use log::{error, trace};
+use std::{
+    net::SocketAddr,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::sync::broadcast;
+
+/// An established QUIC connection to a peer, authenticated by the `PeerId` they presented on it.
+#[derive(Clone)]
+pub struct Connection {
+    quic_conn: quinn::Connection,
+    peer_id: PeerId,
+    max_msg_size: usize,
+}
+
+impl Connection {
+    pub(crate) async fn new(quic_conn: quinn::Connection, max_msg_size: usize) -> Result<Self> {
+        let peer_id = extract_peer_id(&quic_conn)?;
+        Ok(Self {
+            quic_conn,
+            peer_id,
+            max_msg_size,
+        })
+    }
+
+    /// The `PeerId` this peer authenticated itself with.
+    pub fn peer_id(&self) -> PeerId {
+        self.peer_id
+    }
+
+    /// The address of the peer at the other end of this connection.
+    pub fn remote_address(&self) -> SocketAddr {
+        self.quic_conn.remote_address()
+    }
+
+    /// Send `msg` to the peer on a fresh unidirectional stream. The peer has no way to reply on
+    /// it - use `send_bi` if you need a response.
+    pub async fn send_uni(&self, msg: Bytes) -> Result<()> {
+        let mut send = self.quic_conn.open_uni().await?;
+        send.write_all(&msg).await?;
+        send.finish().await?;
+        Ok(())
+    }
+
+    /// Send `msg` to the peer on a fresh bidirectional stream and wait for their reply.
+    pub async fn send_bi(&self, msg: Bytes) -> Result<Bytes> {
+        let wire_msg = Bytes::from(bincode::serialize(&BiStreamMessage::User(msg))?);
+        let response = self.send_bi_raw(wire_msg).await?;
+
+        match bincode::deserialize(&response)? {
+            BiStreamMessage::User(bytes) => Ok(bytes),
+            _ => Err(Error::EchoServiceFailure(
+                "peer replied on a bi-stream with something other than a user message"
+                    .to_string(),
+            )),
+        }
+    }
+
+    // Send `msg` on a fresh bidirectional stream exactly as given, and return the peer's reply
+    // exactly as received, with no `BiStreamMessage` wrapping - used internally by
+    // `api::query_echo_service`, which handles its own `BiStreamMessage` encoding since it needs
+    // to send an `EchoServiceReq` rather than a `User` message.
+    pub(crate) async fn send_bi_raw(&self, msg: Bytes) -> Result<Bytes> {
+        let (mut send, recv) = self.quic_conn.open_bi().await?;
+        send.write_all(&msg).await?;
+        send.finish().await?;
+        let response = recv.read_to_end(self.max_msg_size).await?;
+        Ok(Bytes::from(response))
+    }
+}
+
+// Pull the `PeerId` out of the certificate chain a peer presented when this connection was
+// established - `quinn` hands it back to us as the `rustls::Certificate`s our `PeerCertVerifier`
+// already checked.
+fn extract_peer_id(quic_conn: &quinn::Connection) -> Result<PeerId> {
+    let presented_certs = quic_conn
+        .peer_identity()
+        .and_then(|identity| identity.downcast::<Vec<rustls::Certificate>>().ok())
+        .ok_or_else(|| {
+            Error::InvalidPeerCertificate("peer presented no certificate".to_string())
+        })?;
+
+    let cert = presented_certs.get(0).ok_or_else(|| {
+        Error::InvalidPeerCertificate("peer presented no certificate".to_string())
+    })?;
+
+    peer_config::extract_peer_id(cert)
+}
+
+/// The sending half of a bidirectional stream a peer opened to us, handed back through
+/// `Message::BiStream` so the caller can reply to whoever sent it.
+pub struct SendStream(quinn::SendStream);
+
+impl SendStream {
+    /// Send `msg` back to the peer that opened this stream.
+    pub async fn send(&mut self, msg: Bytes) -> Result<()> {
+        let wire_msg = bincode::serialize(&BiStreamMessage::User(msg))?;
+        self.0.write_all(&wire_msg).await?;
+        self.0.finish().await?;
+        Ok(())
+    }
+}
+
+/// Stream of `Message`s received on connections accepted by an `Endpoint`.
+///
+/// Every accepted connection is authenticated the same way as one we dial out, and is watched for
+/// disconnection via the `Endpoint`'s `DisconnectionEvents`. Echo service requests (see
+/// `api::query_echo_service`) are answered here directly and never surfaced as a `Message`.
+pub struct IncomingConnections(mpsc::UnboundedReceiver<Message>);
+
+impl IncomingConnections {
+    pub(crate) fn new(
+        mut quic_incoming: quinn::Incoming,
+        max_msg_size: usize,
+        disconnect_tx: broadcast::Sender<SocketAddr>,
+    ) -> Result<Self> {
+        let (message_tx, message_rx) = mpsc::unbounded();
+
+        let _ = tokio::spawn(async move {
+            while let Some(connecting) = quic_incoming.next().await {
+                let message_tx = message_tx.clone();
+                let disconnect_tx = disconnect_tx.clone();
+                let _ = tokio::spawn(async move {
+                    if let Err(err) =
+                        accept_connection(connecting, max_msg_size, message_tx, disconnect_tx).await
+                    {
+                        error!("Failed to accept incoming connection: {}", err);
+                    }
+                });
+            }
+        });
+
+        Ok(Self(message_rx))
+    }
+}
+
+impl Stream for IncomingConnections {
+    type Item = Message;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.0).poll_next(cx)
+    }
+}
+
+// Finish accepting `connecting`, authenticate it into a `Connection`, start watching it for
+// disconnection, and spawn the tasks that dispatch its uni- and bi-directional streams.
+async fn accept_connection(
+    connecting: quinn::Connecting,
+    max_msg_size: usize,
+    message_tx: mpsc::UnboundedSender<Message>,
+    disconnect_tx: broadcast::Sender<SocketAddr>,
+) -> Result<()> {
+    let quinn::NewConnection {
+        connection: quic_conn,
+        uni_streams,
+        bi_streams,
+        ..
+    } = connecting.await?;
+
+    let peer_addr = quic_conn.remote_address();
+    trace!("Accepted connection from {}", peer_addr);
+
+    watch_for_disconnection(quic_conn.clone(), peer_addr, disconnect_tx);
+
+    let connection = Connection::new(quic_conn, max_msg_size).await?;
+
+    spawn_uni_stream_dispatcher(connection.clone(), uni_streams, message_tx.clone());
+    spawn_bi_stream_dispatcher(connection, bi_streams, message_tx);
+
+    Ok(())
+}
+
+// Read every uni-directional stream the peer opens to completion and forward it as a
+// `Message::UniStream`.
+fn spawn_uni_stream_dispatcher(
+    connection: Connection,
+    mut uni_streams: quinn::IncomingUniStreams,
+    message_tx: mpsc::UnboundedSender<Message>,
+) {
+    let _ = tokio::spawn(async move {
+        while let Some(result) = uni_streams.next().await {
+            let recv = match result {
+                Ok(recv) => recv,
+                Err(err) => {
+                    trace!(
+                        "Uni-stream from {} closed: {}",
+                        connection.remote_address(),
+                        err
+                    );
+                    break;
+                }
+            };
+
+            match recv.read_to_end(connection.max_msg_size).await {
+                Ok(bytes) => {
+                    let _ = message_tx.unbounded_send(Message::UniStream {
+                        bytes: Bytes::from(bytes),
+                        src: connection.remote_address(),
+                    });
+                }
+                Err(err) => error!("Failed to read uni-stream message: {}", err),
+            }
+        }
+    });
+}
+
+// Read every bi-directional stream the peer opens to completion, answering it ourselves if it's
+// an `EchoServiceReq`, otherwise unwrapping the `User` payload and forwarding it as a
+// `Message::BiStream`.
+fn spawn_bi_stream_dispatcher(
+    connection: Connection,
+    mut bi_streams: quinn::IncomingBiStreams,
+    message_tx: mpsc::UnboundedSender<Message>,
+) {
+    let _ = tokio::spawn(async move {
+        while let Some(result) = bi_streams.next().await {
+            let (send, recv) = match result {
+                Ok(streams) => streams,
+                Err(err) => {
+                    trace!(
+                        "Bi-stream from {} closed: {}",
+                        connection.remote_address(),
+                        err
+                    );
+                    break;
+                }
+            };
+
+            let connection = connection.clone();
+            let message_tx = message_tx.clone();
+            let _ = tokio::spawn(async move {
+                if let Err(err) = dispatch_bi_stream(connection, send, recv, message_tx).await {
+                    error!("Failed to handle bi-stream message: {}", err);
+                }
+            });
+        }
+    });
+}
+
+async fn dispatch_bi_stream(
+    connection: Connection,
+    mut send: quinn::SendStream,
+    recv: quinn::RecvStream,
+    message_tx: mpsc::UnboundedSender<Message>,
+) -> Result<()> {
+    let bytes = recv.read_to_end(connection.max_msg_size).await?;
+
+    match bincode::deserialize(&bytes)? {
+        BiStreamMessage::EchoServiceReq => {
+            let response = echo_service_response(connection.remote_address())?;
+            send.write_all(&response).await?;
+            send.finish().await?;
+        }
+        BiStreamMessage::User(bytes) => {
+            let _ = message_tx.unbounded_send(Message::BiStream {
+                bytes,
+                src: connection.remote_address(),
+                send: SendStream(send),
+            });
+        }
+        BiStreamMessage::EchoServiceResp(_) => trace!(
+            "Ignoring an echo service response arriving as a fresh incoming bi-stream from {}",
+            connection.remote_address()
+        ),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,120 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::api::PeerId;
+use std::fmt;
+
+/// The type returned by the public API, with `Error` as its default error type.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Errors returned by this crate.
+#[derive(Debug)]
+pub enum Error {
+    /// Failure binding or configuring the underlying UDP socket.
+    Endpoint(std::io::Error),
+    /// A miscellaneous I/O failure, e.g. reading the bound socket's local address.
+    Io(std::io::Error),
+    /// Supplied or derived configuration was invalid.
+    Configuration {
+        /// Human-readable description of what was wrong.
+        e: String,
+    },
+    /// None of the nodes we attempted to bootstrap against could be reached.
+    BootstrapFailure,
+    /// `Endpoint::listen` was called more than once on the same endpoint - `quinn::Incoming` is a
+    /// single-consumer stream.
+    EndpointAlreadyListening,
+    /// Failed to establish a QUIC connection.
+    Connect(quinn::ConnectError),
+    /// A QUIC connection failed or was closed.
+    Connection(quinn::ConnectionError),
+    /// Failed to write to a QUIC stream.
+    Write(quinn::WriteError),
+    /// Failed to read a QUIC stream to completion.
+    Read(quinn::ReadToEndError),
+    /// A peer's certificate didn't embed a valid, self-consistent `PeerId`, or our own
+    /// certificate could not be generated or installed.
+    InvalidPeerCertificate(String),
+    /// `connect_to` was given an `expected_id` that didn't match the peer we actually reached.
+    PeerIdMismatch {
+        /// The `PeerId` the caller expected to connect to.
+        expected: PeerId,
+        /// The `PeerId` the peer we reached actually presented.
+        actual: PeerId,
+    },
+    /// A peer replied on a bidirectional stream with a message of the wrong kind, either during
+    /// the echo service request/response exchange or in reply to `Connection::send_bi`.
+    EchoServiceFailure(String),
+    /// Failed to (de)serialise a message.
+    Bincode(bincode::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Endpoint(err) => write!(f, "failed to bind QUIC endpoint: {}", err),
+            Self::Io(err) => write!(f, "I/O error: {}", err),
+            Self::Configuration { e } => write!(f, "invalid configuration: {}", e),
+            Self::BootstrapFailure => write!(f, "failed to bootstrap to the network"),
+            Self::EndpointAlreadyListening => {
+                write!(f, "this endpoint is already listening for incoming connections")
+            }
+            Self::Connect(err) => write!(f, "failed to connect: {}", err),
+            Self::Connection(err) => write!(f, "connection failed: {}", err),
+            Self::Write(err) => write!(f, "failed to write to stream: {}", err),
+            Self::Read(err) => write!(f, "failed to read stream: {}", err),
+            Self::InvalidPeerCertificate(e) => write!(f, "invalid peer certificate: {}", e),
+            Self::PeerIdMismatch { expected, actual } => write!(
+                f,
+                "peer id mismatch: expected {}, got {}",
+                expected, actual
+            ),
+            Self::EchoServiceFailure(e) => write!(f, "echo service failure: {}", e),
+            Self::Bincode(err) => write!(f, "failed to (de)serialise message: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<quinn::ConnectError> for Error {
+    fn from(err: quinn::ConnectError) -> Self {
+        Self::Connect(err)
+    }
+}
+
+impl From<quinn::ConnectionError> for Error {
+    fn from(err: quinn::ConnectionError) -> Self {
+        Self::Connection(err)
+    }
+}
+
+impl From<quinn::WriteError> for Error {
+    fn from(err: quinn::WriteError) -> Self {
+        Self::Write(err)
+    }
+}
+
+impl From<quinn::ReadToEndError> for Error {
+    fn from(err: quinn::ReadToEndError) -> Self {
+        Self::Read(err)
+    }
+}
+
+impl From<bincode::Error> for Error {
+    fn from(err: bincode::Error) -> Self {
+        Self::Bincode(err)
+    }
+}
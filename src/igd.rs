@@ -0,0 +1,59 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+//! UPnP/IGD port forwarding, used by `QuicP2p::our_endpoint` to make us reachable from behind a
+//! home router without the user having to forward the port themselves. Only built when the
+//! `upnp` feature is enabled.
+
+use super::error::{Error, Result};
+use igd::PortMappingProtocol;
+use log::trace;
+use std::net::{IpAddr, SocketAddrV4, UdpSocket};
+
+/// Ask the LAN's UPnP/IGD-capable gateway to forward `port` (UDP) to us for `lease_duration`
+/// seconds. The mapping auto-expires once the lease elapses rather than being held forever, so a
+/// node that crashes without cleaning up after itself doesn't leave a stale mapping behind; a
+/// long-running node is expected to call this again well before the lease runs out.
+pub(crate) async fn forward_port(port: u16, lease_duration: u32) -> Result<()> {
+    let gateway = igd::search_gateway(Default::default()).map_err(|e| Error::Configuration {
+        e: format!("failed to find an IGD gateway: {}", e),
+    })?;
+
+    let local_addr = match get_local_ip()? {
+        IpAddr::V4(ip) => SocketAddrV4::new(ip, port),
+        IpAddr::V6(_) => {
+            return Err(Error::Configuration {
+                e: "IGD port mapping is only supported over IPv4".to_string(),
+            })
+        }
+    };
+
+    gateway
+        .add_port(
+            PortMappingProtocol::UDP,
+            port,
+            local_addr,
+            lease_duration,
+            "quic-p2p",
+        )
+        .map_err(|e| Error::Configuration {
+            e: format!("failed to map port via IGD: {}", e),
+        })?;
+
+    trace!("Mapped port {} via IGD for {}s", port, lease_duration);
+    Ok(())
+}
+
+/// The IP address of the network interface used to reach the default gateway - what we advertise
+/// to the router as our local endpoint when requesting a port mapping.
+pub(crate) fn get_local_ip() -> Result<IpAddr> {
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    socket.connect("8.8.8.8:80")?;
+    Ok(socket.local_addr()?.ip())
+}
@@ -0,0 +1,202 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+//! Peer identity and QUIC/TLS configuration.
+//!
+//! Every node holds a long-term Ed25519 keypair. Instead of trusting a CA or checking a
+//! hostname, we generate a self-signed certificate that embeds our public key in a custom
+//! extension and is signed by the matching private key, and install a `rustls` verifier that
+//! extracts that key, checks the certificate's self-signature against it, and derives the
+//! peer's `PeerId` from it. This is the same approach rust-libp2p's QUIC transport uses.
+
+use super::{
+    api::PeerId,
+    error::{Error, Result},
+};
+use quinn::{ClientConfig, ClientConfigBuilder, ServerConfig, ServerConfigBuilder, TransportConfig};
+use rcgen::{CertificateParams, CustomExtension, KeyPair, PKCS_ED25519};
+use ring::signature::{UnparsedPublicKey, ED25519};
+use rustls::{
+    Certificate, CertificateChain, ClientCertVerified, ClientCertVerifier, DistinguishedNames,
+    PrivateKey, RootCertStore, ServerCertVerified, ServerCertVerifier, TLSError,
+};
+use std::{sync::Arc, time::Duration};
+use webpki::{DNSName, DNSNameRef};
+
+/// Default idle timeout applied to both our own and our peers' connections, in milliseconds.
+pub const DEFAULT_IDLE_TIMEOUT_MSEC: u64 = 60_000;
+
+/// Default keep-alive interval we ping peers at, in milliseconds.
+pub const DEFAULT_KEEP_ALIVE_INTERVAL_MSEC: u64 = 20_000;
+
+/// Passed to `quinn::Endpoint::connect_with` as the SNI value. Our custom verifiers authenticate
+/// peers via the public key embedded in their certificate (see `PeerId`), not via this name, but
+/// `rustls`/`quinn` still require some parseable server name to be supplied.
+pub(crate) const SERVER_NAME: &str = "quic-p2p";
+
+// Private enterprise arc used for the custom X.509 extension that embeds a node's raw Ed25519
+// public key in its self-signed certificate.
+const PEER_ID_EXTENSION_OID: &[u64] = &[1, 3, 6, 1, 4, 1, 53, 1, 1];
+
+/// Generate a fresh long-term Ed25519 keypair and a self-signed certificate that embeds its
+/// public key in a custom extension and is signed by the matching private key.
+pub fn generate_self_signed_cert() -> Result<(PrivateKey, Certificate, PeerId)> {
+    let key_pair = KeyPair::generate(&PKCS_ED25519)
+        .map_err(|e| Error::InvalidPeerCertificate(e.to_string()))?;
+    let public_key = key_pair.public_key_raw().to_vec();
+
+    let mut params = CertificateParams::new(vec![SERVER_NAME.to_string()]);
+    params.alg = &PKCS_ED25519;
+    params.custom_extensions = vec![CustomExtension::from_oid_content(
+        PEER_ID_EXTENSION_OID,
+        public_key.clone(),
+    )];
+    params.key_pair = Some(key_pair);
+
+    let cert = rcgen::Certificate::from_params(params)
+        .map_err(|e| Error::InvalidPeerCertificate(e.to_string()))?;
+    let cert_der = cert
+        .serialize_der()
+        .map_err(|e| Error::InvalidPeerCertificate(e.to_string()))?;
+    let key_der = cert.serialize_private_key_der();
+
+    let peer_id = PeerId::from_public_key_bytes(&public_key)?;
+
+    Ok((PrivateKey(key_der), Certificate(cert_der), peer_id))
+}
+
+/// Extract and verify the `PeerId` embedded in a peer's self-signed certificate: pull the public
+/// key out of our custom extension, then check that the certificate really was signed by that
+/// key before trusting it.
+pub fn extract_peer_id(cert: &Certificate) -> Result<PeerId> {
+    let (_, parsed) = x509_parser::parse_x509_certificate(&cert.0)
+        .map_err(|e| Error::InvalidPeerCertificate(e.to_string()))?;
+
+    let public_key_bytes = parsed
+        .tbs_certificate
+        .extensions()
+        .iter()
+        .find(|ext| oid_matches(ext.oid.as_bytes(), PEER_ID_EXTENSION_OID))
+        .map(|ext| ext.value)
+        .ok_or_else(|| Error::InvalidPeerCertificate("missing PeerId extension".to_string()))?;
+
+    UnparsedPublicKey::new(&ED25519, public_key_bytes)
+        .verify(
+            parsed.tbs_certificate.as_ref(),
+            parsed.signature_value.as_ref(),
+        )
+        .map_err(|_| {
+            Error::InvalidPeerCertificate("certificate self-signature check failed".to_string())
+        })?;
+
+    PeerId::from_public_key_bytes(public_key_bytes)
+}
+
+fn oid_matches(encoded: &[u8], oid: &[u64]) -> bool {
+    x509_parser::oid_registry::Oid::from(oid)
+        .map(|expected| expected.as_bytes() == encoded)
+        .unwrap_or(false)
+}
+
+// A `rustls` verifier that authenticates a peer by the `PeerId` embedded in their self-signed
+// certificate instead of checking it against a CA or expected hostname.
+struct PeerCertVerifier;
+
+impl ServerCertVerifier for PeerCertVerifier {
+    fn verify_server_cert(
+        &self,
+        _roots: &RootCertStore,
+        presented_certs: &[Certificate],
+        _dns_name: DNSNameRef,
+        _ocsp_response: &[u8],
+    ) -> std::result::Result<ServerCertVerified, TLSError> {
+        verify_presented_cert(presented_certs)?;
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+impl ClientCertVerifier for PeerCertVerifier {
+    fn client_auth_root_subjects(&self, _dns_name: Option<&DNSName>) -> Option<DistinguishedNames> {
+        Some(DistinguishedNames::new())
+    }
+
+    fn verify_client_cert(
+        &self,
+        presented_certs: &[Certificate],
+        _dns_name: Option<&DNSName>,
+    ) -> std::result::Result<ClientCertVerified, TLSError> {
+        verify_presented_cert(presented_certs)?;
+        Ok(ClientCertVerified::assertion())
+    }
+}
+
+fn verify_presented_cert(presented_certs: &[Certificate]) -> std::result::Result<PeerId, TLSError> {
+    let cert = presented_certs
+        .get(0)
+        .ok_or(TLSError::NoCertificatesPresented)?;
+    extract_peer_id(cert).map_err(|_| TLSError::NoCertificatesPresented)
+}
+
+/// Build our own `quinn::ServerConfig`: presents `cert`/`key` on incoming connections and
+/// requires and authenticates the connecting client's certificate via the same `PeerId` scheme,
+/// rather than trusting a CA.
+pub fn new_our_cfg(
+    idle_timeout_msec: u64,
+    keep_alive_interval_msec: u64,
+    cert: Certificate,
+    key: PrivateKey,
+) -> Result<ServerConfig> {
+    let mut transport_config = TransportConfig::default();
+    let _ = transport_config.max_idle_timeout(Some(Duration::from_millis(idle_timeout_msec)))?;
+    let _ = transport_config.keep_alive_interval(Some(Duration::from_millis(
+        keep_alive_interval_msec,
+    )));
+
+    let mut server_config = ServerConfig::default();
+    server_config.transport = Arc::new(transport_config);
+
+    let mut cfg_builder = ServerConfigBuilder::new(server_config);
+    let _ = cfg_builder.certificate(CertificateChain::from_certs(vec![cert]), key)?;
+
+    Arc::get_mut(&mut cfg_builder.as_mut().crypto)
+        .expect("fresh Arc has no other owners")
+        .set_client_certificate_verifier(Arc::new(PeerCertVerifier));
+
+    Ok(cfg_builder.build())
+}
+
+/// Build our own `quinn::ClientConfig`, authenticating the server we dial via the same `PeerId`
+/// scheme rather than a CA, and presenting `cert`/`key` as our own identity in return - the
+/// `PeerId` scheme is mutual, so our `new_our_cfg` server side mandates a client certificate on
+/// every incoming connection and there would be nothing to authenticate it against otherwise.
+pub fn new_client_cfg(
+    idle_timeout_msec: u64,
+    keep_alive_interval_msec: u64,
+    cert: Certificate,
+    key: PrivateKey,
+) -> Result<ClientConfig> {
+    let mut transport_config = TransportConfig::default();
+    let _ = transport_config.max_idle_timeout(Some(Duration::from_millis(idle_timeout_msec)));
+    let _ = transport_config.keep_alive_interval(Some(Duration::from_millis(
+        keep_alive_interval_msec,
+    )));
+
+    let mut client_config = ClientConfigBuilder::default().build();
+    client_config.transport = Arc::new(transport_config);
+
+    let crypto = Arc::get_mut(&mut client_config.crypto).expect("fresh Arc has no other owners");
+    crypto
+        .dangerous()
+        .set_certificate_verifier(Arc::new(PeerCertVerifier));
+    crypto
+        .set_single_client_cert(vec![cert], key)
+        .map_err(|e| Error::InvalidPeerCertificate(e.to_string()))?;
+
+    Ok(client_config)
+}